@@ -0,0 +1,15 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+mod queue;
+mod runtime;
+
+pub use queue::{
+    CatloopQueue,
+    DuplexPipe,
+    Socket,
+};
+pub use runtime::{
+    CatloopRuntime,
+    SimultaneousOpenRole,
+};
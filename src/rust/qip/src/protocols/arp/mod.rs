@@ -0,0 +1,13 @@
+mod options;
+mod peer;
+mod query;
+
+#[cfg(test)]
+mod tests;
+
+pub use options::ArpOptions;
+pub use peer::{
+    ArpPeer,
+    ArpQueryFuture,
+};
+pub use query::ArpQuery;
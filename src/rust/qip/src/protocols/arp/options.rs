@@ -0,0 +1,55 @@
+use ::float_duration::FloatDuration;
+use ::rand::Rng;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Configuration for the ARP resolver, in particular the retransmission schedule used while
+/// waiting for a reply.
+#[derive(Clone, Debug)]
+pub struct ArpOptions {
+    /// Number of retransmissions to attempt before giving up with `Fail::Timeout`.
+    pub retry_count: Option<u32>,
+    /// Deadline used for the first (`attempt == 0`) retransmission.
+    pub min_timeout: Option<FloatDuration>,
+    /// Upper bound the backoff schedule is clamped to, so later retries don't grow unbounded.
+    pub max_timeout: Option<FloatDuration>,
+    /// Growth factor applied per attempt: `min_timeout * multiplier^attempt`.
+    pub multiplier: Option<f64>,
+}
+
+impl Default for ArpOptions {
+    fn default() -> Self {
+        Self {
+            retry_count: Some(2),
+            min_timeout: Some(FloatDuration::seconds(1.0)),
+            max_timeout: Some(FloatDuration::seconds(4.0)),
+            multiplier: Some(2.0),
+        }
+    }
+}
+
+impl ArpOptions {
+    /// Computes the deadline for retransmission attempt `attempt` (0-indexed), following RFC
+    /// 8305's connection-attempt timing: `min_timeout * multiplier^attempt`, clamped to
+    /// `max_timeout`, then perturbed by uniform +/-10% jitter so that many peers re-ARPing after
+    /// the same link event don't retransmit in lockstep.
+    pub fn backoff_deadline(&self, attempt: u32, rng: &mut impl Rng) -> FloatDuration {
+        let min_timeout: FloatDuration = self.min_timeout.unwrap_or_else(|| FloatDuration::seconds(1.0));
+        let max_timeout: FloatDuration = self.max_timeout.unwrap_or_else(|| FloatDuration::seconds(4.0));
+        let multiplier: f64 = self.multiplier.unwrap_or(2.0);
+
+        let scaled: FloatDuration = FloatDuration::seconds(min_timeout.as_seconds() * multiplier.powi(attempt as i32));
+        let jitter: f64 = rng.gen_range(0.9..=1.1);
+        let jittered: FloatDuration = FloatDuration::seconds(scaled.as_seconds() * jitter);
+
+        // `max_timeout` is a hard ceiling, so clamp after jitter -- otherwise a jitter draw above
+        // 1.0 could push an already-clamped deadline past it.
+        if jittered > max_timeout {
+            max_timeout
+        } else {
+            jittered
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use super::{
+    options::ArpOptions,
+    query::ArpQuery,
+};
+use crate::{
+    prelude::*,
+    protocols::ethernet2,
+};
+use ::std::{
+    cell::RefCell,
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    net::Ipv4Addr,
+    rc::Rc,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Shared state behind an [ArpPeer] and its outstanding [ArpQueryFuture]s.
+struct Inner {
+    local_link_addr: MacAddress,
+    local_ipv4_addr: Ipv4Addr,
+    options: ArpOptions,
+    /// Resolved IPv4-to-link-address mappings.
+    cache: HashMap<Ipv4Addr, MacAddress>,
+    /// In-flight resolutions, keyed by target. Each tracks its own backoff schedule via [ArpQuery].
+    queries: HashMap<Ipv4Addr, ArpQuery>,
+    /// Packets queued for transmission, drained one at a time by `poll`.
+    outbox: VecDeque<Vec<u8>>,
+}
+
+/// Resolves IPv4 addresses to link-layer addresses via ARP. Retransmits unanswered requests
+/// using the backoff schedule configured in `options` (see [ArpOptions::backoff_deadline]),
+/// rather than a fixed interval.
+#[derive(Clone)]
+pub struct ArpPeer(Rc<RefCell<Inner>>);
+
+/// A pending resolution for a single target IPv4 address, returned by [ArpPeer::arp_query].
+pub struct ArpQueryFuture {
+    target_ipv4: Ipv4Addr,
+    inner: Rc<RefCell<Inner>>,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+impl ArpPeer {
+    pub fn new(local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr, options: ArpOptions) -> Self {
+        Self(Rc::new(RefCell::new(Inner {
+            local_link_addr,
+            local_ipv4_addr,
+            options,
+            cache: HashMap::new(),
+            queries: HashMap::new(),
+            outbox: VecDeque::new(),
+        })))
+    }
+
+    /// Returns this peer's ARP configuration.
+    pub fn options(&self) -> ArpOptions {
+        self.0.borrow().options.clone()
+    }
+
+    /// Returns a snapshot of the resolved-address cache.
+    pub fn export_arp_cache(&self) -> HashMap<Ipv4Addr, MacAddress> {
+        self.0.borrow().cache.clone()
+    }
+
+    /// Starts resolving `target_ipv4`. Queues the initial ARP request for the next `poll` and
+    /// returns a future that resolves once a reply is cached, retransmitting on the backoff
+    /// schedule from `options.arp` until `retry_count` is exhausted.
+    pub fn arp_query(&mut self, target_ipv4: Ipv4Addr) -> ArpQueryFuture {
+        {
+            let mut inner = self.0.borrow_mut();
+            let request: Vec<u8> = encode_arp_packet(
+                ArpOp::Request,
+                inner.local_link_addr,
+                inner.local_ipv4_addr,
+                None,
+                target_ipv4,
+            );
+            inner.outbox.push_back(request);
+            let options: ArpOptions = inner.options.clone();
+            inner.queries.insert(target_ipv4, ArpQuery::new(Instant::now(), target_ipv4, options));
+        }
+        ArpQueryFuture {
+            target_ipv4,
+            inner: self.0.clone(),
+        }
+    }
+
+    /// Returns the next queued effect (i.e. a packet to transmit), if any.
+    pub fn poll(&mut self, _now: Instant) -> Option<Effect> {
+        self.0.borrow_mut().outbox.pop_front().map(Effect::Transmit)
+    }
+
+    /// Processes an incoming ARP packet. Learns the sender's mapping if we're the target or
+    /// already have the sender cached, queuing a reply if we're the target of a request.
+    /// Otherwise the packet is `Fail::Ignored`.
+    pub fn receive(&mut self, bytes: &mut [u8]) -> Result<(), Fail> {
+        let packet: ArpPacket = decode_arp_packet(bytes)?;
+        let mut inner = self.0.borrow_mut();
+
+        let is_target: bool = packet.target_ipv4 == inner.local_ipv4_addr;
+        let already_known: bool = inner.cache.contains_key(&packet.sender_ipv4);
+        if !is_target && !already_known {
+            return Err(Fail::Ignored {});
+        }
+
+        inner.cache.insert(packet.sender_ipv4, packet.sender_link_addr);
+
+        if is_target && packet.op == ArpOp::Request {
+            let reply: Vec<u8> = encode_arp_packet(
+                ArpOp::Reply,
+                inner.local_link_addr,
+                inner.local_ipv4_addr,
+                Some(packet.sender_link_addr),
+                packet.sender_ipv4,
+            );
+            inner.outbox.push_back(reply);
+        }
+
+        Ok(())
+    }
+}
+
+impl ArpQueryFuture {
+    /// Polls this resolution. Returns `Ok(link_addr)` once resolved, `Err(Fail::TryAgain)` while
+    /// still waiting (queuing a retransmission if the current backoff deadline has elapsed), or
+    /// `Err(Fail::Timeout)` once `retry_count` attempts have gone unanswered.
+    pub fn poll(&self, now: Instant) -> Result<MacAddress, Fail> {
+        let mut inner = self.inner.borrow_mut();
+
+        if let Some(link_addr) = inner.cache.get(&self.target_ipv4).copied() {
+            inner.queries.remove(&self.target_ipv4);
+            return Ok(link_addr);
+        }
+
+        let target_ipv4: Ipv4Addr = self.target_ipv4;
+        let due: bool = match inner.queries.get(&target_ipv4) {
+            Some(query) => query.is_due(now),
+            None => return Err(Fail::Timeout {}),
+        };
+
+        if !due {
+            return Err(Fail::TryAgain {});
+        }
+
+        let (local_link_addr, local_ipv4_addr) = (inner.local_link_addr, inner.local_ipv4_addr);
+        let advanced: Result<(), ()> = inner
+            .queries
+            .get_mut(&target_ipv4)
+            .expect("checked above")
+            .advance(now);
+
+        match advanced {
+            Ok(()) => {
+                let request: Vec<u8> =
+                    encode_arp_packet(ArpOp::Request, local_link_addr, local_ipv4_addr, None, target_ipv4);
+                inner.outbox.push_back(request);
+                Err(Fail::TryAgain {})
+            },
+            Err(()) => {
+                inner.queries.remove(&target_ipv4);
+                Err(Fail::Timeout {})
+            },
+        }
+    }
+}
+
+//======================================================================================================================
+// Packet Encoding
+//======================================================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ArpOp {
+    Request,
+    Reply,
+}
+
+struct ArpPacket {
+    op: ArpOp,
+    sender_link_addr: MacAddress,
+    sender_ipv4: Ipv4Addr,
+    target_ipv4: Ipv4Addr,
+}
+
+fn encode_arp_packet(
+    op: ArpOp,
+    sender_link_addr: MacAddress,
+    sender_ipv4: Ipv4Addr,
+    target_link_addr: Option<MacAddress>,
+    target_ipv4: Ipv4Addr,
+) -> Vec<u8> {
+    // 1 (op) + 6 (sender link addr) + 4 (sender ipv4) + 6 (target link addr) + 4 (target ipv4).
+    let mut packet: Vec<u8> = Vec::with_capacity(21);
+    packet.push(if op == ArpOp::Request { 0 } else { 1 });
+    packet.extend_from_slice(&sender_link_addr.octets());
+    packet.extend_from_slice(&sender_ipv4.octets());
+    packet.extend_from_slice(&target_link_addr.unwrap_or(sender_link_addr).octets());
+    packet.extend_from_slice(&target_ipv4.octets());
+    // Pad up to the minimum payload a frame can carry, same as a real link layer would.
+    packet.resize(::std::cmp::max(packet.len(), ethernet2::MIN_PAYLOAD_SIZE), 0);
+    packet
+}
+
+fn decode_arp_packet(bytes: &[u8]) -> Result<ArpPacket, Fail> {
+    if bytes.len() < 21 {
+        return Err(Fail::Ignored {});
+    }
+    let op: ArpOp = match bytes[0] {
+        0 => ArpOp::Request,
+        1 => ArpOp::Reply,
+        _ => return Err(Fail::Ignored {}),
+    };
+    let sender_link_addr: MacAddress = MacAddress::new([bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6]]);
+    let sender_ipv4: Ipv4Addr = Ipv4Addr::new(bytes[7], bytes[8], bytes[9], bytes[10]);
+    let target_ipv4: Ipv4Addr = Ipv4Addr::new(bytes[17], bytes[18], bytes[19], bytes[20]);
+    Ok(ArpPacket {
+        op,
+        sender_link_addr,
+        sender_ipv4,
+        target_ipv4,
+    })
+}
@@ -0,0 +1,66 @@
+use super::options::ArpOptions;
+use ::rand::{
+    rngs::SmallRng,
+    SeedableRng,
+};
+use ::std::{
+    net::Ipv4Addr,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// In-flight ARP resolution for `target_ipv4`. Tracks the retransmission attempt count and the
+/// deadline for the next retry under the backoff schedule in [ArpOptions], replacing the old
+/// fixed-interval `request_timeout` retry loop.
+pub struct ArpQuery {
+    target_ipv4: Ipv4Addr,
+    options: ArpOptions,
+    rng: SmallRng,
+    attempt: u32,
+    deadline: Instant,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+impl ArpQuery {
+    /// Starts a new query for `target_ipv4`, scheduling the first (`attempt == 0`) retransmission
+    /// deadline.
+    pub fn new(now: Instant, target_ipv4: Ipv4Addr, options: ArpOptions) -> Self {
+        let mut rng: SmallRng = SmallRng::from_entropy();
+        let deadline: Instant = now + options.backoff_deadline(0, &mut rng).to_std().unwrap();
+        Self {
+            target_ipv4,
+            options,
+            rng,
+            attempt: 0,
+            deadline,
+        }
+    }
+
+    pub fn target_ipv4(&self) -> Ipv4Addr {
+        self.target_ipv4
+    }
+
+    /// Whether `now` has reached the current retransmission deadline.
+    pub fn is_due(&self, now: Instant) -> bool {
+        now >= self.deadline
+    }
+
+    /// Advances to the next retransmission attempt, scheduling its backed-off, jittered deadline.
+    /// Returns `Err(())` once `attempt == retry_count`, at which point the caller should give up
+    /// with `Fail::Timeout` instead of retransmitting again.
+    pub fn advance(&mut self, now: Instant) -> Result<(), ()> {
+        let retry_count: u32 = self.options.retry_count.unwrap_or(0);
+        if self.attempt >= retry_count {
+            return Err(());
+        }
+        self.attempt += 1;
+        self.deadline = now + self.options.backoff_deadline(self.attempt, &mut self.rng).to_std().unwrap();
+        Ok(())
+    }
+}
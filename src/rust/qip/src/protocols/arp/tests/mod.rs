@@ -3,6 +3,22 @@ use float_duration::FloatDuration;
 use serde_yaml;
 use std::time::{Duration, Instant};
 
+/// Upper bound on the interval `arp_query` can wait before retransmitting for the
+/// given `attempt` (0-indexed), i.e. `min_timeout * multiplier^attempt` clamped to
+/// `max_timeout`, inflated by the `+/-10%` jitter window. Advancing the clock by at
+/// least this much guarantees the retransmission deadline has passed regardless of
+/// the jitter actually drawn.
+fn max_backoff(options: &Options, attempt: u32) -> Duration {
+    let min_timeout: FloatDuration = options.arp.min_timeout.unwrap();
+    let max_timeout: FloatDuration = options.arp.max_timeout.unwrap();
+    let multiplier: f64 = options.arp.multiplier.unwrap();
+
+    let scaled = FloatDuration::seconds(min_timeout.as_seconds() * multiplier.powi(attempt as i32));
+    let clamped = if scaled > max_timeout { max_timeout } else { scaled };
+    let jittered = FloatDuration::seconds(clamped.as_seconds() * 1.1);
+    jittered.to_std().unwrap()
+}
+
 #[test]
 fn immediate_reply() {
     // tests to ensure that an are request results in a reply.
@@ -14,9 +30,14 @@ fn immediate_reply() {
     // this test is written based on certain assumptions.
     let options = alice.options();
     assert_eq!(
-        options.arp.request_timeout.unwrap(),
+        options.arp.min_timeout.unwrap(),
         FloatDuration::seconds(1.0)
     );
+    assert_eq!(
+        options.arp.max_timeout.unwrap(),
+        FloatDuration::seconds(4.0)
+    );
+    assert_eq!(options.arp.multiplier.unwrap(), 2.0);
 
     let fut = alice.arp_query(*test::carrie_ipv4_addr());
     let now = now + Duration::from_millis(1);
@@ -79,13 +100,13 @@ fn slow_reply() {
     let options = alice.options();
     assert!(options.arp.retry_count.unwrap() > 0);
     assert_eq!(
-        options.arp.request_timeout.unwrap(),
+        options.arp.min_timeout.unwrap(),
         FloatDuration::seconds(1.0)
     );
 
     let fut = alice.arp_query(*test::carrie_ipv4_addr());
-    // move time forward enough to trigger a timeout.
-    let now = now + Duration::from_secs(1);
+    // move time forward enough to trigger the first backoff deadline, regardless of jitter.
+    let now = now + max_backoff(&options, 0);
     match fut.poll(now) {
         Err(Fail::TryAgain {}) => (),
         x => panic!("expected Fail::TryAgain {{}}, got `{:?}`", x),
@@ -143,28 +164,31 @@ fn no_reply() {
     let options = alice.options();
     assert_eq!(options.arp.retry_count.unwrap(), 2);
     assert_eq!(
-        options.arp.request_timeout.unwrap(),
+        options.arp.min_timeout.unwrap(),
         FloatDuration::seconds(1.0)
     );
 
     let fut = alice.arp_query(*test::carrie_ipv4_addr());
 
-    // move time forward enough to trigger a timeout.
-    let now = now + Duration::from_secs(1);
+    // move time forward enough to trigger the backed-off deadline for each attempt in turn.
+    // the interval grows by `multiplier` per attempt (clamped to `max_timeout`), so we can't
+    // advance by a flat duration like the fixed-timeout version of this test did.
+    let now = now + max_backoff(&options, 0);
     match fut.poll(now) {
         Err(Fail::TryAgain {}) => (),
         x => panic!("expected Fail::TryAgain {{}}, got `{:?}`", x),
     }
 
     // retry #1
-    let now = now + Duration::from_secs(1);
+    let now = now + max_backoff(&options, 1);
     match fut.poll(now) {
         Err(Fail::TryAgain {}) => (),
         x => panic!("expected Fail::TryAgain {{}}, got `{:?}`", x),
     }
 
-    // retry #2
-    let now = now + Duration::from_secs(1);
+    // retry #2: the backoff deadline still has to elapse before the future notices
+    // `attempt == retry_count` and gives up.
+    let now = now + max_backoff(&options, 2);
     match fut.poll(now) {
         Err(Fail::Timeout {}) => (),
         x => panic!("expected Fail::Timeout {{}}, got `{:?}`", x),